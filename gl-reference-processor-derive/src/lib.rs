@@ -0,0 +1,266 @@
+//! `#[derive(FromTokens)]` turns a struct or enum definition into a
+//! `FromTokens::parse` implementation, so new grammar productions in the
+//! parser crate can be declared instead of hand-matched against `Ts`.
+//!
+//! Struct fields are consumed in declaration order. A field tagged
+//! `#[token(Text)]` / `#[token(Number)]` requires the next token to carry
+//! that payload and binds it into the field; `#[token("const")]` requires
+//! the next token to be the literal `Text("const")` and is consumed without
+//! binding (the field type must be `()`). A field with no `#[token(..)]`
+//! attribute is parsed by recursing into `<FieldType as FromTokens>::parse`.
+//!
+//! Enum variants are tuple variants carrying a `#[tokens(...)]` list
+//! describing their full token sequence, e.g. `#[tokens("const", Text,
+//! Asterisk)]`. Entries that are string literals or payload-free token
+//! names (`Asterisk`, `OpeningParenthesis`, ...) are consumed without
+//! binding; entries naming a payload-bearing token (`Text`, `Number`) are
+//! consumed and bound into the next tuple field, in order. Variants are
+//! tried in declaration order on a speculative cursor, so earlier variants
+//! should be the more specific ones (mirroring how the hand-written slice
+//! patterns they replace were ordered).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta};
+
+#[proc_macro_derive(FromTokens, attributes(token, tokens))]
+pub fn derive_from_tokens(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let lifetime = match lifetime_param(&input.generics) {
+        Ok(lifetime) => lifetime,
+        Err(error) => return error.into(),
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => derive_struct(&data.fields, &lifetime),
+        Data::Enum(data) => derive_enum(&input.ident, data, &lifetime),
+        Data::Union(_) => {
+            syn::Error::new_spanned(&input, "FromTokens does not support unions").to_compile_error()
+        }
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics crate::FromTokens<#lifetime> for #ident #ty_generics #where_clause {
+            fn parse(ts: &mut crate::Ts<#lifetime>) -> Result<Self, crate::ParseError> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// The deriving type's single lifetime parameter, whatever it's spelled
+/// (`'s`, `'source`, ...), so the generated `impl` borrows from the same
+/// lifetime the type itself declares instead of assuming a fixed name.
+fn lifetime_param(generics: &syn::Generics) -> Result<syn::Lifetime, TokenStream2> {
+    match generics.lifetimes().collect::<Vec<_>>().as_slice() {
+        [single] => Ok(single.lifetime.clone()),
+        [] => Err(syn::Error::new_spanned(
+            generics,
+            "FromTokens requires the deriving type to declare a lifetime parameter",
+        )
+        .to_compile_error()),
+        _ => Err(syn::Error::new_spanned(
+            generics,
+            "FromTokens does not support types with more than one lifetime parameter",
+        )
+        .to_compile_error()),
+    }
+}
+
+/// A single entry of a `#[token(..)]` / `#[tokens(..)]` attribute: either a
+/// literal `Text` value to match exactly, or the name of a `Token` variant
+/// to match structurally (binding its payload if it carries one).
+enum TokenSpec {
+    Literal(String),
+    Kind(syn::Ident),
+}
+
+fn parse_token_specs(meta: &Meta) -> Vec<TokenSpec> {
+    let list = match meta {
+        Meta::List(list) => list,
+        _ => return Vec::new(),
+    };
+    list.parse_args_with(syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+        .map(|exprs| {
+            exprs
+                .into_iter()
+                .filter_map(|expr| match expr {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => Some(TokenSpec::Literal(s.value())),
+                    syn::Expr::Path(path) => path.path.get_ident().cloned().map(TokenSpec::Kind),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn token_attr(attrs: &[syn::Attribute]) -> Option<Vec<TokenSpec>> {
+    attrs.iter().find_map(|attr| {
+        if attr.path().is_ident("token") || attr.path().is_ident("tokens") {
+            Some(parse_token_specs(&attr.meta))
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether a bare `Token` variant name carries a payload that should be
+/// bound into a field, as opposed to a punctuation/marker token that is
+/// only ever consumed.
+fn is_payload_token(kind: &syn::Ident) -> bool {
+    matches!(kind.to_string().as_str(), "Text" | "Number")
+}
+
+/// Emit the statements that consume one token matching `spec` from `ts`,
+/// binding it to `binding` (a fresh local) when it carries a payload.
+fn consume_token(spec: &TokenSpec, binding: &syn::Ident) -> TokenStream2 {
+    match spec {
+        TokenSpec::Literal(text) => quote! {
+            let #binding = match ts.split_first() {
+                Some((crate::Spanned { token: crate::Token::Text(#text), .. }, rest)) => {
+                    *ts = rest;
+                }
+                _ => return Err(crate::ParseError::new(
+                    concat!("expected literal `", #text, "`"),
+                    crate::span_of(ts),
+                )),
+            };
+        },
+        TokenSpec::Kind(kind) if is_payload_token(kind) => quote! {
+            let #binding = match ts.split_first() {
+                Some((crate::Spanned { token: crate::Token::#kind(value), .. }, rest)) => {
+                    let value = *value;
+                    *ts = rest;
+                    value
+                }
+                _ => return Err(crate::ParseError::new(
+                    concat!("expected ", stringify!(#kind)),
+                    crate::span_of(ts),
+                )),
+            };
+        },
+        TokenSpec::Kind(kind) => quote! {
+            let #binding = match ts.split_first() {
+                Some((crate::Spanned { token: crate::Token::#kind, .. }, rest)) => {
+                    *ts = rest;
+                }
+                _ => return Err(crate::ParseError::new(
+                    concat!("expected ", stringify!(#kind)),
+                    crate::span_of(ts),
+                )),
+            };
+        },
+    }
+}
+
+fn derive_struct(fields: &Fields, lifetime: &syn::Lifetime) -> TokenStream2 {
+    let Fields::Named(fields) = fields else {
+        return syn::Error::new_spanned(
+            quote! { #fields },
+            "FromTokens only supports structs with named fields",
+        )
+        .to_compile_error();
+    };
+
+    let mut steps = Vec::new();
+    let mut field_idents = Vec::new();
+
+    for field in &fields.named {
+        let ident = field.ident.clone().expect("named field");
+        field_idents.push(ident.clone());
+
+        match token_attr(&field.attrs) {
+            Some(specs) => {
+                let spec = specs
+                    .first()
+                    .expect("#[token(..)] expects one entry on a field");
+                steps.push(consume_token(spec, &ident));
+            }
+            None => {
+                let ty = &field.ty;
+                steps.push(quote! {
+                    let #ident = <#ty as crate::FromTokens<#lifetime>>::parse(ts)?;
+                });
+            }
+        }
+    }
+
+    quote! {
+        #(#steps)*
+        Ok(Self { #(#field_idents),* })
+    }
+}
+
+fn derive_enum(enum_ident: &syn::Ident, data: &syn::DataEnum, lifetime: &syn::Lifetime) -> TokenStream2 {
+    let mut attempts = Vec::new();
+
+    for variant in &data.variants {
+        let variant_ident = &variant.ident;
+        let Fields::Unnamed(fields) = &variant.fields else {
+            attempts.push(
+                syn::Error::new_spanned(variant, "FromTokens only supports tuple enum variants")
+                    .to_compile_error(),
+            );
+            continue;
+        };
+
+        let specs = token_attr(&variant.attrs).unwrap_or_default();
+        let mut steps = Vec::new();
+        let mut bindings = Vec::new();
+        let mut field_index = 0;
+
+        for spec in &specs {
+            let binding = quote::format_ident!("_field_{}", steps.len());
+            steps.push(consume_token(spec, &binding));
+            let binds_payload = matches!(spec, TokenSpec::Kind(kind) if is_payload_token(kind));
+            if binds_payload {
+                bindings.push(binding);
+                field_index += 1;
+            }
+        }
+
+        if field_index != fields.unnamed.len() {
+            attempts.push(
+                syn::Error::new_spanned(
+                    variant,
+                    "the number of payload-bearing #[tokens(..)] entries must match the variant's fields",
+                )
+                .to_compile_error(),
+            );
+            continue;
+        }
+
+        // Try this variant on a speculative copy of the cursor so a
+        // mismatch can fall through to the next variant instead of
+        // failing the whole parse, mirroring how the hand-written slice
+        // patterns this replaces were tried in order.
+        attempts.push(quote! {
+            let attempt: Result<(Self, crate::Ts<#lifetime>), crate::ParseError> = (|| {
+                let mut cursor = *ts;
+                let ts = &mut cursor;
+                #(#steps)*
+                Ok((#enum_ident::#variant_ident(#(#bindings),*), cursor))
+            })();
+            if let Ok((value, advanced)) = attempt {
+                *ts = advanced;
+                return Ok(value);
+            }
+        });
+    }
+
+    quote! {
+        let start = *ts;
+        #(#attempts)*
+        Err(crate::ParseError::new(
+            concat!("no variant of ", stringify!(#enum_ident), " matched"),
+            crate::span_of(start),
+        ))
+    }
+}
@@ -6,7 +6,7 @@ pub trait IndentationInfo {
     fn is_dedent(marker: char) -> bool;
 }
 
-struct VariantIndentationInfo;
+pub struct VariantIndentationInfo;
 
 impl VariantIndentationInfo {
     const INDENTS: &'static [char] = &['{', '['];
@@ -19,11 +19,35 @@ impl IndentationInfo for VariantIndentationInfo {
     fn is_dedent(marker: char) -> bool { Self::DEDENTS.contains(&marker) }
 }
 
-struct SplitOnLevel<'a, I = VariantIndentationInfo> {
+/// Like [`VariantIndentationInfo`], but also tracks `()` nesting so a
+/// parameter list can be split on commas without breaking apart a
+/// parenthesized sub-group.
+pub struct ParameterIndentationInfo;
+
+impl ParameterIndentationInfo {
+    const INDENTS: &'static [char] = &['{', '[', '('];
+    const DEDENTS: &'static [char] = &['}', ']', ')'];
+}
+
+impl IndentationInfo for ParameterIndentationInfo {
+    fn is_indent(marker: char) -> bool { Self::INDENTS.contains(&marker) }
+
+    fn is_dedent(marker: char) -> bool { Self::DEDENTS.contains(&marker) }
+}
+
+/// Returned in place of the next segment when [`SplitOnLevel`] finds a
+/// closing bracket with no matching opening one, so malformed input from
+/// an untrusted reference file can be reported instead of aborting the
+/// whole run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnbalancedBracketError;
+
+pub struct SplitOnLevel<'a, I = VariantIndentationInfo> {
     offset: usize,
     depth: usize,
     haystack: &'a str,
     sep: char,
+    finished: bool,
     _indent_phantom: PhantomData<I>
 }
 
@@ -31,34 +55,91 @@ impl<'a, I> Iterator for SplitOnLevel<'a, I>
 where
     I: IndentationInfo
 {
-    type Item = &'a str;
+    type Item = Result<&'a str, UnbalancedBracketError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.haystack.is_empty() { return None; }
-        while let [char, tail @ ..] = self.haystack {
-            if I::is_indent(char) {
+        if self.finished { return None; }
+
+        for (index, marker) in self.haystack[self.offset..].char_indices() {
+            let index = self.offset + index;
+            if I::is_indent(marker) {
                 self.depth += 1;
-            }
-            if I::is_dedent(char) {
-                self.depth -= 1;
-            }
-            if self.depth == 0 && char == self.sep {
-                let old_offset = self.offset;
-                self.offset = index + 1;
-                self.haystack = 
-                return Some(&self.haystack[old_offset..index]);
+            } else if I::is_dedent(marker) {
+                match self.depth.checked_sub(1) {
+                    Some(depth) => self.depth = depth,
+                    None => {
+                        self.finished = true;
+                        return Some(Err(UnbalancedBracketError));
+                    }
+                }
+            } else if self.depth == 0 && marker == self.sep {
+                let segment = &self.haystack[self.offset..index];
+                self.offset = index + marker.len_utf8();
+                return Some(Ok(segment));
             }
         }
-        Some(self.haystack)
+
+        self.finished = true;
+        Some(Ok(&self.haystack[self.offset..]))
     }
 }
 
+/// Split `input` on `sep`, but only at bracket-nesting depth zero, so a
+/// separator inside a `{}`/`[]`/`()` group (per `I`) doesn't split the
+/// group apart, e.g. `"a,{b,c},d"` -> `["a", "{b,c}", "d"]`.
+pub fn split_on_level<I: IndentationInfo>(input: &str, sep: char) -> SplitOnLevel<'_, I> {
+    SplitOnLevel {
+        offset: 0,
+        depth: 0,
+        haystack: input,
+        sep,
+        finished: false,
+        _indent_phantom: PhantomData,
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::{split_on_level, ParameterIndentationInfo, UnbalancedBracketError, VariantIndentationInfo};
 
-fn split_on_level<T, P, I>(input: &str, pat: P)
-where
-    P: sep,
-    I: IndentationInfo
-{
+    #[test]
+    fn splits_nested_separators_at_top_level_only() {
+        let result = split_on_level::<VariantIndentationInfo>("a,{b,c},d", ',')
+            .collect::<Result<Vec<_>, _>>()
+            .expect("brackets are balanced");
+        assert_eq!(result, ["a", "{b,c}", "d"]);
+    }
+
+    #[test]
+    fn yields_whole_haystack_when_separator_absent() {
+        let result = split_on_level::<VariantIndentationInfo>("just one piece", ',')
+            .collect::<Result<Vec<_>, _>>()
+            .expect("brackets are balanced");
+        assert_eq!(result, ["just one piece"]);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn yields_trailing_segment_exactly_once() {
+        let result = split_on_level::<VariantIndentationInfo>("a,b", ',')
+            .collect::<Result<Vec<_>, _>>()
+            .expect("brackets are balanced");
+        assert_eq!(result, ["a", "b"]);
+    }
+
+    #[test]
+    fn splits_on_parenthesis_nesting_for_parameter_groups() {
+        let result = split_on_level::<ParameterIndentationInfo>(
+            "uint a,(sizei b,sizei c),uint d",
+            ',',
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .expect("brackets are balanced");
+        assert_eq!(result, ["uint a", "(sizei b,sizei c)", "uint d"]);
+    }
+
+    #[test]
+    fn unbalanced_closing_bracket_yields_error_instead_of_panicking() {
+        let result: Vec<_> = split_on_level::<VariantIndentationInfo>("a}", ',').collect();
+        assert_eq!(result, [Err(UnbalancedBracketError)]);
+    }
+}